@@ -1,82 +1,374 @@
 // User contract API
+
+use std::fmt;
+
+/// Maximum number of signature requests accepted in a single `sign_many` call.
+/// Bounds gas usage so a large batch cannot run out of gas mid-loop.
+const MAX_BATCH_SIZE: usize = 32;
+
+/// Gas reserved for [`VersionedMpcContract::return_signatures_for_batch`],
+/// the callback that assembles a `sign_many` batch's collected signatures
+/// once every sub-request has resolved.
+const RETURN_SIGNATURES_FOR_BATCH_GAS: Gas = Gas::from_tgas(5);
+
+/// Domain separator tag mixed into every [`DelegatedSignRequest`] signing
+/// payload, versioned so the scheme can change without colliding with old
+/// signatures. Bumped whenever the signed payload's shape changes.
+const DELEGATED_SIGN_REQUEST_DOMAIN: &str = "near-mpc.sign_delegated.v1";
+
+/// Concrete V1 contract state. The domain/request bookkeeping
+/// (`requests`, populated by the existing `add_request`) predates this
+/// series of changes; `batches` is the bookkeeping `sign_many` needs to
+/// collect a batch's signatures once every sub-request resolves.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct MpcContractV1 {
+    pub(crate) requests: near_sdk::store::IterableMap<CryptoHash, SignatureRequest>,
+    /// `sign_many` batch id -> the `return_sig_id`s of its sub-requests, in
+    /// request order, until every one of them has resolved.
+    pub(crate) batches: near_sdk::store::LookupMap<CryptoHash, Vec<CryptoHash>>,
+    /// Prepaid balance an account can draw `sign`/`sign_many`/`sign_delegated`
+    /// fees from instead of attaching a deposit to every call.
+    pub(crate) balances: near_sdk::store::LookupMap<AccountId, NearToken>,
+    /// Governance-settable congestion-fee curve; see [`SignatureFeeConfig`].
+    pub(crate) signature_fee: SignatureFeeConfig,
+    /// Replay-protection nonce per signer, for [`VersionedMpcContract::sign_delegated`].
+    pub(crate) nonces: near_sdk::store::LookupMap<AccountId, u64>,
+    /// The ED25519 key each account has registered for
+    /// [`VersionedMpcContract::sign_delegated`] via
+    /// [`VersionedMpcContract::register_delegation_key`]. `sign_delegated`
+    /// verifies against this, never a key carried on the request itself.
+    pub(crate) delegation_keys: near_sdk::store::LookupMap<AccountId, PublicKey>,
+}
+
+/// The congestion-fee curve `required_signature_fee` evaluates:
+/// `required_deposit = base_fee + slope * pending_requests`, saturating at
+/// `max_fee`. Settable via
+/// [`VersionedMpcContract::set_signature_fee_config`].
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SignatureFeeConfig {
+    pub base_fee: NearToken,
+    pub slope: NearToken,
+    pub max_fee: NearToken,
+}
+
+impl Default for SignatureFeeConfig {
+    fn default() -> Self {
+        Self {
+            base_fee: NearToken::from_yoctonear(1),
+            slope: NearToken::from_yoctonear(1),
+            max_fee: NearToken::from_millinear(1),
+        }
+    }
+}
+
+impl MpcContractV1 {
+    /// Records `return_sig_ids` (one per sub-request, in request order) under
+    /// `batch_id` so they can be collected once every sub-request resolves.
+    pub(crate) fn add_batch(&mut self, batch_id: CryptoHash, return_sig_ids: Vec<CryptoHash>) {
+        self.batches.insert(batch_id, return_sig_ids);
+    }
+
+    /// Removes and returns the `return_sig_id`s stored for `batch_id`, or
+    /// `None` if the batch is unknown (e.g. already collected).
+    pub(crate) fn take_batch(&mut self, batch_id: &CryptoHash) -> Option<Vec<CryptoHash>> {
+        self.batches.remove(batch_id)
+    }
+
+    /// Number of signature requests currently in flight (created by
+    /// `add_request`, not yet resolved/cleaned up). Feeds the congestion
+    /// component of `required_signature_fee`.
+    pub(crate) fn num_pending_requests(&self) -> u64 {
+        self.requests.len() as u64
+    }
+
+    /// Returns `account_id`'s current prepaid balance, or zero if they have
+    /// never deposited.
+    pub(crate) fn balance_of(&self, account_id: &AccountId) -> NearToken {
+        self.balances
+            .get(account_id)
+            .copied()
+            .unwrap_or(NearToken::from_yoctonear(0))
+    }
+
+    /// Credits `amount` to `account_id`'s prepaid balance. Saturates instead
+    /// of overflowing: NEAR's total token supply is well below `u128::MAX`,
+    /// so saturation is unreachable in practice but keeps the arithmetic
+    /// honest rather than panicking on a future unit change.
+    pub(crate) fn credit_balance(&mut self, account_id: &AccountId, amount: NearToken) {
+        let current = self.balance_of(account_id);
+        let updated =
+            NearToken::from_yoctonear(current.as_yoctonear().saturating_add(amount.as_yoctonear()));
+        self.balances.insert(account_id.clone(), updated);
+    }
+
+    /// Attempts to debit `amount` from `account_id`'s prepaid balance.
+    /// Returns `false` (and leaves the balance untouched) if the balance is
+    /// insufficient, rather than underflowing.
+    pub(crate) fn debit_balance(&mut self, account_id: &AccountId, amount: NearToken) -> bool {
+        let current = self.balance_of(account_id);
+        let Some(updated) = current.checked_sub(amount) else {
+            return false;
+        };
+        if updated == NearToken::from_yoctonear(0) {
+            self.balances.remove(account_id);
+        } else {
+            self.balances.insert(account_id.clone(), updated);
+        }
+        true
+    }
+
+    /// The nonce `signer_id` must use in their next `sign_delegated` call.
+    pub(crate) fn next_nonce(&self, signer_id: &AccountId) -> u64 {
+        self.nonces.get(signer_id).copied().unwrap_or(0)
+    }
+
+    /// Checks `nonce` against `signer_id`'s next expected nonce and, if it
+    /// matches, advances it so the same nonce cannot be used again.
+    pub(crate) fn check_and_increment_nonce(&mut self, signer_id: &AccountId, nonce: u64) -> bool {
+        if nonce != self.next_nonce(signer_id) {
+            return false;
+        }
+        self.nonces
+            .insert(signer_id.clone(), nonce.saturating_add(1));
+        true
+    }
+
+    /// Returns the ED25519 key `signer_id` has registered for
+    /// `sign_delegated`, if any.
+    pub(crate) fn delegation_key_of(&self, signer_id: &AccountId) -> Option<&PublicKey> {
+        self.delegation_keys.get(signer_id)
+    }
+
+    /// Registers `public_key` as the key `signer_id` will use to sign
+    /// `sign_delegated` requests, replacing any previously registered key.
+    pub(crate) fn register_delegation_key(&mut self, signer_id: AccountId, public_key: PublicKey) {
+        self.delegation_keys.insert(signer_id, public_key);
+    }
+
+    /// Verifies a [`DelegatedSignRequest`] end to end: `deadline` has not
+    /// passed, its signature matches the ED25519 key `signer_id` registered
+    /// via [`Self::register_delegation_key`] (never a key carried on the
+    /// request itself — see [`VersionedMpcContract::sign_delegated`]), and
+    /// its nonce is the signer's next expected one (checked and advanced
+    /// atomically with the signature check). Does not validate
+    /// `signed_request.request` itself, which is domain-specific and left to
+    /// the caller.
+    pub(crate) fn verify_delegated_request(
+        &mut self,
+        contract_account_id: &AccountId,
+        signed_request: &DelegatedSignRequest,
+    ) -> Result<(), SignError> {
+        if env::block_timestamp() > signed_request.deadline {
+            return Err(SignError::Expired {
+                deadline: signed_request.deadline,
+            });
+        }
+
+        let public_key = self
+            .delegation_key_of(&signed_request.signer_id)
+            .cloned()
+            .ok_or(SignError::UnregisteredDelegationKey)?;
+
+        // The registered delegation key is assumed ED25519, same as NEAR's own
+        // meta-transaction (delegate action) signing; a key of any other
+        // curve fails here as `InvalidSignature`.
+        let public_key_bytes: [u8; 32] = public_key.as_bytes()[1..]
+            .try_into()
+            .map_err(|_| SignError::InvalidSignature)?;
+        let signature: [u8; 64] = signed_request
+            .signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignError::InvalidSignature)?;
+        // Bind the domain tag and the contract's own account id into the
+        // signed payload so a captured signed request cannot be replayed
+        // against a different deployment (e.g. another instance of this
+        // contract where the signer registered the same key and nonce).
+        let message = serde_json::to_vec(&(
+            DELEGATED_SIGN_REQUEST_DOMAIN,
+            contract_account_id,
+            &signed_request.request,
+            &signed_request.nonce,
+            &signed_request.deadline,
+        ))
+        .unwrap();
+        if !env::ed25519_verify(&signature, &message, &public_key_bytes) {
+            return Err(SignError::InvalidSignature);
+        }
+
+        if !self.check_and_increment_nonce(&signed_request.signer_id, signed_request.nonce) {
+            return Err(SignError::NonceMismatch {
+                expected: self.next_nonce(&signed_request.signer_id),
+                provided: signed_request.nonce,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors returned by [`VersionedMpcContract::sign`] and
+/// [`VersionedMpcContract::sign_many`]. These surface as structured,
+/// machine-parseable errors in the transaction outcome instead of opaque
+/// panic strings, while failing identically to how the MPC nodes would fail
+/// so users still get an actionable message.
+#[derive(Debug, Clone)]
+pub enum SignError {
+    /// No key was found for the requested domain.
+    DomainNotFound { domain_id: DomainId },
+    /// The payload could not be interpreted for its domain's curve. Also
+    /// reported per entry by `return_signatures_for_batch` for a sub-request
+    /// that failed or whose signature could not be deserialized.
+    MalformedPayload(String),
+    /// The payload's curve does not match the domain's curve.
+    CurveMismatch,
+    /// Not enough gas was attached to complete the yield/resume flow.
+    InsufficientGas { provided: Gas, required: Gas },
+    /// Not enough deposit (or prepaid balance) was attached to cover the fee.
+    InsufficientDeposit {
+        provided: NearToken,
+        required: NearToken,
+    },
+    /// A delegated request's signature did not verify against its claimed signer.
+    InvalidSignature,
+    /// A delegated request's `signer_id` has not registered a key via
+    /// `register_delegation_key`, so `sign_delegated` has nothing to verify
+    /// the signature against.
+    UnregisteredDelegationKey,
+    /// A delegated request's `deadline` has already passed.
+    Expired { deadline: u64 },
+    /// A delegated request's `nonce` does not match the signer's next nonce,
+    /// i.e. it has already been used or was submitted out of order.
+    NonceMismatch { expected: u64, provided: u64 },
+    /// `sign_many` was called with zero requests.
+    EmptyBatch,
+    /// `sign_many` was called with more requests than `MAX_BATCH_SIZE`.
+    BatchTooLarge { provided: usize, max: usize },
+    /// `return_signatures_for_batch` was invoked for a `batch_id` that is
+    /// not (or no longer) present in storage.
+    UnknownBatch,
+    /// The contract is not on the expected `V1` state version.
+    InvalidContractState,
+    /// The caller is not permitted to perform a self-call-gated action.
+    Unauthorized,
+}
+
+impl fmt::Display for SignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignError::DomainNotFound { domain_id } => write!(
+                f,
+                "No key was found for the provided domain_id {domain_id:?}."
+            ),
+            SignError::MalformedPayload(reason) => write!(f, "Malformed payload: {reason}"),
+            SignError::CurveMismatch => {
+                write!(f, "Payload does not match the domain's curve")
+            }
+            SignError::InsufficientGas { provided, required } => {
+                write!(f, "Provided: {provided}, required: {required}")
+            }
+            SignError::InsufficientDeposit { provided, required } => {
+                write!(f, "Require a deposit of {required}, found: {provided}")
+            }
+            SignError::InvalidSignature => {
+                write!(
+                    f,
+                    "Delegated request signature does not match the claimed signer"
+                )
+            }
+            SignError::UnregisteredDelegationKey => {
+                write!(f, "Signer has not registered a key for sign_delegated")
+            }
+            SignError::Expired { deadline } => {
+                write!(f, "Delegated request expired at {deadline}")
+            }
+            SignError::NonceMismatch { expected, provided } => {
+                write!(f, "Expected nonce {expected} for signer, found: {provided}")
+            }
+            SignError::EmptyBatch => write!(f, "Batch requires at least one request"),
+            SignError::BatchTooLarge { provided, max } => {
+                write!(f, "Batch size {provided} exceeds the maximum of {max}")
+            }
+            SignError::UnknownBatch => write!(f, "Unknown or already-collected batch_id"),
+            SignError::InvalidContractState => {
+                write!(f, "Contract is not on the expected V1 state version")
+            }
+            SignError::Unauthorized => {
+                write!(f, "Caller is not permitted to perform this action")
+            }
+        }
+    }
+}
+
+impl FunctionError for SignError {
+    fn panic(&self) -> ! {
+        env::panic_str(&self.to_string())
+    }
+}
+
+/// A `sign` request the end user has signed off-chain so that a relayer can
+/// submit it and pay the gas/fee on their behalf, mirroring the
+/// forwarder/typed-data execute pattern: verify signature + nonce, then
+/// forward the call using the *original* signer's identity.
+///
+/// The signature covers the client/contract-matching encoding of
+/// `(DELEGATED_SIGN_REQUEST_DOMAIN, current_account_id, request, nonce,
+/// deadline)` (the encoding is `serde_json`, not a canonical form — it must
+/// simply match what the signer produced, which is fine as long as
+/// `SignRequestArgs` itself has no ambiguous encodings such as unordered
+/// maps). Binding the domain tag and this contract's own account id means a
+/// signed request cannot be replayed against a different deployment (e.g.
+/// another instance of this contract where the signer registered the same
+/// key and nonce); the nonce means it cannot be replayed against this one
+/// either, and `deadline` bounds how long a relayer can sit on it before
+/// submitting.
+#[derive(Debug, Clone)]
+pub struct DelegatedSignRequest {
+    pub request: SignRequestArgs,
+    /// The signature is verified against the ED25519 key `signer_id`
+    /// registered via [`VersionedMpcContract::register_delegation_key`], not
+    /// a key carried on this request, so a relayer cannot forward a request
+    /// under someone else's identity using a key of their own choosing.
+    pub signer_id: AccountId,
+    /// Must equal [`VersionedMpcContract::next_nonce`] for `signer_id`.
+    pub nonce: u64,
+    /// Block timestamp (nanoseconds) past which the request can no longer be relayed.
+    pub deadline: u64,
+    pub signature: Vec<u8>,
+}
+
 #[near_bindgen]
 impl VersionedMpcContract {
     /// `key_version` must be less than or equal to the value at `latest_key_version`
     /// To avoid overloading the network with too many requests,
     /// we ask for a small deposit for each signature request.
-    /// The fee changes based on how busy the network is.
+    /// The fee changes based on how busy the network is: see
+    /// [`Self::experimental_signature_deposit`].
     #[handle_result]
     #[payable]
-    pub fn sign(&mut self, request: SignRequestArgs) {
+    pub fn sign(&mut self, request: SignRequestArgs) -> Result<(), SignError> {
         log!(
             "sign: predecessor={:?}, request={:?}",
             env::predecessor_account_id(),
             request
         );
 
-        let request: SignRequest = request.try_into().unwrap();
-        let Ok(public_key) = self.public_key(Some(request.domain_id)) else {
-            env::panic_str(
-                &InvalidParameters::DomainNotFound
-                    .message(format!(
-                        "No key was found for the provided domain_id {:?}.",
-                        request.domain_id,
-                    ))
-                    .to_string(),
-            );
-        };
-
-        let curve_type = public_key.curve_type();
-
-        // ensure the signer sent a valid signature request
-        // It's important we fail here because the MPC nodes will fail in an identical way.
-        // This allows users to get the error message
-        match &curve_type {
-            CurveType::SECP256K1 => {
-                let hash = *request.payload.as_ecdsa().expect("Payload is not Ecdsa");
-                k256::Scalar::from_repr(hash.into())
-                    .into_option()
-                    .expect("Ecdsa payload cannot be converted to Scalar");
-            }
-            CurveType::ED25519 => {
-                request.payload.as_eddsa().expect("Payload is not EdDSA");
-            }
-        }
+        let request: SignRequest = request
+            .try_into()
+            .map_err(|e| SignError::MalformedPayload(format!("{e:?}")))?;
+        self.validate_sign_request(&request)?;
 
         // Make sure sign call will not run out of gas doing yield/resume logic
         if env::prepaid_gas() < GAS_FOR_SIGN_CALL {
-            env::panic_str(
-                &InvalidParameters::InsufficientGas
-                    .message(format!(
-                        "Provided: {}, required: {}",
-                        env::prepaid_gas(),
-                        GAS_FOR_SIGN_CALL
-                    ))
-                    .to_string(),
-            );
+            return Err(SignError::InsufficientGas {
+                provided: env::prepaid_gas(),
+                required: GAS_FOR_SIGN_CALL,
+            });
         }
 
         let predecessor = env::predecessor_account_id();
-        // Check deposit and refund if required
-        let deposit = env::attached_deposit();
-        match deposit.checked_sub(NearToken::from_yoctonear(1)) {
-            None => {
-                env::panic_str(
-                    &InvalidParameters::InsufficientDeposit
-                        .message(format!(
-                            "Require a deposit of 1 yoctonear, found: {}",
-                            deposit.as_yoctonear(),
-                        ))
-                        .to_string(),
-                );
-            }
-            Some(diff) => {
-                if diff > NearToken::from_yoctonear(0) {
-                    log!("refund excess deposit {diff} to {predecessor}");
-                    Promise::new(predecessor.clone()).transfer(diff);
-                }
-            }
-        }
+        let required_fee = self.required_signature_fee()?;
+        self.charge_fee(&predecessor, required_fee)?;
 
         let request = SignatureRequest::new(
             request.domain_id,
@@ -86,7 +378,7 @@ impl VersionedMpcContract {
         );
 
         let Self::V1(mpc_contract) = self else {
-            env::panic_str("expected V1")
+            return Err(SignError::InvalidContractState);
         };
 
         env::log_str(&serde_json::to_string(&near_sdk::env::random_seed_array()).unwrap());
@@ -109,5 +401,704 @@ impl VersionedMpcContract {
         }
 
         env::promise_return(promise_index);
+        Ok(())
+    }
+
+    /// Batched version of [`Self::sign`] for callers that need one signature
+    /// per input (e.g. one per Bitcoin UTXO) and would otherwise have to
+    /// split a single logical operation across many transactions.
+    ///
+    /// Every payload is validated against its domain's curve before any
+    /// yield/resume is created, so a single invalid request rejects the whole
+    /// batch instead of leaving some sub-requests dangling. Deposit and gas
+    /// are required in proportion to the batch size; excess deposit is
+    /// refunded once, for the batch as a whole.
+    #[handle_result]
+    #[payable]
+    pub fn sign_many(&mut self, requests: Vec<SignRequestArgs>) -> Result<(), SignError> {
+        if requests.is_empty() {
+            return Err(SignError::EmptyBatch);
+        }
+        if requests.len() > MAX_BATCH_SIZE {
+            return Err(SignError::BatchTooLarge {
+                provided: requests.len(),
+                max: MAX_BATCH_SIZE,
+            });
+        }
+
+        log!(
+            "sign_many: predecessor={:?}, batch_size={}",
+            env::predecessor_account_id(),
+            requests.len()
+        );
+
+        let requests: Vec<SignRequest> = requests
+            .into_iter()
+            .map(|request| {
+                request
+                    .try_into()
+                    .map_err(|e| SignError::MalformedPayload(format!("{e:?}")))
+            })
+            .collect::<Result<_, _>>()?;
+
+        // Validate every payload up front: a malformed request anywhere in
+        // the batch must reject the whole batch before any yield is created.
+        for request in &requests {
+            self.validate_sign_request(request)?;
+        }
+
+        // Make sure the whole batch will not run out of gas doing yield/resume logic
+        let required_gas = GAS_FOR_SIGN_CALL * (requests.len() as u64);
+        if env::prepaid_gas() < required_gas {
+            return Err(SignError::InsufficientGas {
+                provided: env::prepaid_gas(),
+                required: required_gas,
+            });
+        }
+
+        let predecessor = env::predecessor_account_id();
+        // The batch requires the current per-signature congestion fee, once per
+        // request; saturate rather than panic if that would overflow.
+        let required_deposit = NearToken::from_yoctonear(
+            self.required_signature_fee()?
+                .as_yoctonear()
+                .saturating_mul(requests.len() as u128),
+        );
+        self.charge_fee(&predecessor, required_deposit)?;
+
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+
+        let batch_id: CryptoHash = env::random_seed_array();
+        let mut promise_indices = Vec::with_capacity(requests.len());
+        let mut return_sig_ids = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            let request = SignatureRequest::new(
+                request.domain_id,
+                request.payload,
+                &predecessor,
+                &request.path,
+            );
+
+            let promise_index = env::promise_yield_create(
+                "return_signature_and_clean_state_on_success",
+                &serde_json::to_vec(&(&request,)).unwrap(),
+                RETURN_SIGNATURE_AND_CLEAN_STATE_ON_SUCCESS_CALL_GAS,
+                GasWeight(0),
+                DATA_ID_REGISTER,
+            );
+
+            let return_sig_id: CryptoHash = env::read_register(DATA_ID_REGISTER)
+                .expect("read_register failed")
+                .try_into()
+                .expect("conversion to CryptoHash failed");
+            if mpc_contract.add_request(&request, return_sig_id) {
+                log!("request already present, overriding callback.")
+            }
+
+            promise_indices.push(promise_index);
+            return_sig_ids.push(return_sig_id);
+        }
+
+        // Keyed by batch id so `return_sig_id`s for the whole batch can be
+        // looked up once every sub-request resolves.
+        mpc_contract.add_batch(batch_id, return_sig_ids);
+
+        // Join every sub-request's yield/resume promise in one `promise_and`
+        // call, then collect their resolved signatures into the batch's
+        // return value once all of them are ready. A single `promise_and`
+        // over the whole slice is required here (rather than e.g. folding
+        // pairwise with repeated two-promise `promise_and` calls): only the
+        // flat form guarantees `promise_result(i)` lines up with
+        // `promise_indices[i]`, which `return_signatures_for_batch` relies on
+        // to return signatures in request order.
+        let joined = env::promise_and(&promise_indices);
+        let collect = env::promise_then(
+            joined,
+            env::current_account_id(),
+            "return_signatures_for_batch",
+            &serde_json::to_vec(&(&batch_id,)).unwrap(),
+            NearToken::from_yoctonear(0),
+            RETURN_SIGNATURES_FOR_BATCH_GAS,
+        );
+        env::promise_return(collect);
+        Ok(())
+    }
+
+    /// Reads every sub-request's resolved signature for `batch_id` and
+    /// returns them in request order, removing the batch's bookkeeping.
+    /// Scheduled as the `.then()` callback on the joined yield/resume promise
+    /// created by [`Self::sign_many`], so it only runs once every signature
+    /// in the batch is ready.
+    ///
+    /// Results are per-entry, not all-or-nothing: a promise's own panic
+    /// would revert the `take_batch` removal along with it (NEAR reverts a
+    /// receipt's state writes when it panics), and since each sub-request's
+    /// yield/resume promise can only be resolved once, a reverted batch
+    /// could never be collected again. Once the batch is found, returning
+    /// `Ok` with per-entry failures reported inline keeps the `take_batch`
+    /// removal committed no matter how many sub-requests failed. An `Err`
+    /// is returned only before anything is removed: for a non-`V1` state or
+    /// an unknown `batch_id`.
+    #[private]
+    #[handle_result]
+    pub fn return_signatures_for_batch(
+        &mut self,
+        batch_id: CryptoHash,
+    ) -> Result<Vec<Result<Signature, SignError>>, SignError> {
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        let return_sig_ids = mpc_contract
+            .take_batch(&batch_id)
+            .ok_or(SignError::UnknownBatch)?;
+
+        Ok((0..return_sig_ids.len())
+            .map(|i| match env::promise_result(i as u64) {
+                PromiseResult::Successful(value) => serde_json::from_slice(&value)
+                    .map_err(|e| SignError::MalformedPayload(format!("{e:?}"))),
+                _ => Err(SignError::MalformedPayload(
+                    "a signature request in the batch failed".to_string(),
+                )),
+            })
+            .collect())
+    }
+
+    /// Gasless relay entry point: verifies the end user's signature over
+    /// `signed_request`, checks and increments their replay-protection nonce,
+    /// rejects the request if `deadline` has passed, and then forwards it as
+    /// a `sign` call using the *original signer's* account id and path (not
+    /// `env::predecessor_account_id()`), so key derivation is unchanged for
+    /// the true owner. The fee is charged to the relayer (the predecessor),
+    /// via their attached deposit or prepaid balance, so apps can sponsor
+    /// signatures for their users.
+    #[handle_result]
+    #[payable]
+    pub fn sign_delegated(
+        &mut self,
+        signed_request: DelegatedSignRequest,
+    ) -> Result<(), SignError> {
+        log!(
+            "sign_delegated: relayer={:?}, signer={:?}",
+            env::predecessor_account_id(),
+            signed_request.signer_id
+        );
+
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        mpc_contract.verify_delegated_request(&env::current_account_id(), &signed_request)?;
+
+        let request: SignRequest = signed_request
+            .request
+            .try_into()
+            .map_err(|e| SignError::MalformedPayload(format!("{e:?}")))?;
+        self.validate_sign_request(&request)?;
+
+        if env::prepaid_gas() < GAS_FOR_SIGN_CALL {
+            return Err(SignError::InsufficientGas {
+                provided: env::prepaid_gas(),
+                required: GAS_FOR_SIGN_CALL,
+            });
+        }
+
+        let relayer = env::predecessor_account_id();
+        let required_fee = self.required_signature_fee()?;
+        self.charge_fee(&relayer, required_fee)?;
+
+        let request = SignatureRequest::new(
+            request.domain_id,
+            request.payload,
+            &signed_request.signer_id,
+            &request.path,
+        );
+
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+
+        let promise_index = env::promise_yield_create(
+            "return_signature_and_clean_state_on_success",
+            &serde_json::to_vec(&(&request,)).unwrap(),
+            RETURN_SIGNATURE_AND_CLEAN_STATE_ON_SUCCESS_CALL_GAS,
+            GasWeight(0),
+            DATA_ID_REGISTER,
+        );
+
+        let return_sig_id: CryptoHash = env::read_register(DATA_ID_REGISTER)
+            .expect("read_register failed")
+            .try_into()
+            .expect("conversion to CryptoHash failed");
+        if mpc_contract.add_request(&request, return_sig_id) {
+            log!("request already present, overriding callback.")
+        }
+
+        env::promise_return(promise_index);
+        Ok(())
+    }
+
+    /// Returns the nonce `signer_id` must use in their next
+    /// [`DelegatedSignRequest`] for [`Self::sign_delegated`].
+    #[handle_result]
+    pub fn next_nonce(&self, signer_id: AccountId) -> Result<u64, SignError> {
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        Ok(mpc_contract.next_nonce(&signer_id))
+    }
+
+    /// Registers `public_key` as the caller's key for future
+    /// `sign_delegated` calls, replacing any key registered previously. Must
+    /// be called directly by the account itself (never relayed), which is
+    /// what lets `sign_delegated` trust the registration as proof the caller
+    /// controls `signer_id`, instead of trusting a key carried on the
+    /// delegated request.
+    #[handle_result]
+    pub fn register_delegation_key(&mut self, public_key: PublicKey) -> Result<(), SignError> {
+        let signer_id = env::predecessor_account_id();
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        mpc_contract.register_delegation_key(signer_id, public_key);
+        Ok(())
+    }
+
+    /// Credits the attached deposit to the caller's prepaid balance so future
+    /// `sign`/`sign_many` calls can debit the fee without attaching a deposit
+    /// each time. Lets high-volume signers (bots, relayers) top up once and
+    /// stream requests.
+    #[handle_result]
+    #[payable]
+    pub fn deposit(&mut self) -> Result<(), SignError> {
+        let predecessor = env::predecessor_account_id();
+        let amount = env::attached_deposit();
+        if amount == NearToken::from_yoctonear(0) {
+            return Err(SignError::InsufficientDeposit {
+                provided: amount,
+                required: NearToken::from_yoctonear(1),
+            });
+        }
+
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        mpc_contract.credit_balance(&predecessor, amount);
+        log!("deposit: credited {amount} to {predecessor}'s prepaid balance");
+        Ok(())
+    }
+
+    /// Withdraws `amount` from the caller's prepaid balance and transfers it
+    /// back to them.
+    #[handle_result]
+    pub fn withdraw(&mut self, amount: NearToken) -> Result<(), SignError> {
+        let predecessor = env::predecessor_account_id();
+
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        if !mpc_contract.debit_balance(&predecessor, amount) {
+            return Err(SignError::InsufficientDeposit {
+                provided: mpc_contract.balance_of(&predecessor),
+                required: amount,
+            });
+        }
+        log!("withdraw: refunding {amount} to {predecessor}");
+        Promise::new(predecessor.clone())
+            .transfer(amount)
+            .then(Self::ext(env::current_account_id()).on_withdraw_transfer(predecessor, amount));
+        Ok(())
+    }
+
+    /// Re-credits `amount` back to `account_id`'s prepaid balance if the
+    /// transfer scheduled by [`Self::withdraw`] failed, so a failed transfer
+    /// cannot silently burn the withdrawn balance.
+    #[private]
+    #[handle_result]
+    pub fn on_withdraw_transfer(
+        &mut self,
+        account_id: AccountId,
+        amount: NearToken,
+    ) -> Result<(), SignError> {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            let Self::V1(mpc_contract) = self else {
+                return Err(SignError::InvalidContractState);
+            };
+            mpc_contract.credit_balance(&account_id, amount);
+            log!("withdraw: transfer to {account_id} failed, re-credited {amount}");
+        }
+        Ok(())
+    }
+
+    /// Returns `account_id`'s current prepaid balance.
+    #[handle_result]
+    pub fn balance_of(&self, account_id: AccountId) -> Result<NearToken, SignError> {
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        Ok(mpc_contract.balance_of(&account_id))
+    }
+
+    /// Returns the per-signature fee a caller must currently attach (or have
+    /// available in their prepaid balance) to `sign`; `sign_many` requires
+    /// it once per request in the batch. Acts like a
+    /// gas-price oracle: clients query this before calling and attach at
+    /// least this amount, since the fee grows with how many requests are
+    /// currently in flight.
+    #[handle_result]
+    pub fn experimental_signature_deposit(&self) -> Result<NearToken, SignError> {
+        self.required_signature_fee()
+    }
+
+    /// Computes `base_fee + slope * pending_requests`, saturating at
+    /// `max_fee`, using the governance-settable [`SignatureFeeConfig`].
+    /// This is the per-signature congestion fee.
+    fn required_signature_fee(&self) -> Result<NearToken, SignError> {
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        let fee_config = &mpc_contract.signature_fee;
+        let pending = mpc_contract.num_pending_requests() as u128;
+        let congestion = fee_config.slope.as_yoctonear().saturating_mul(pending);
+        let fee = fee_config
+            .base_fee
+            .as_yoctonear()
+            .saturating_add(congestion)
+            .min(fee_config.max_fee.as_yoctonear());
+        Ok(NearToken::from_yoctonear(fee))
+    }
+
+    /// Returns the current governance-settable congestion-fee curve.
+    #[handle_result]
+    pub fn signature_fee_config(&self) -> Result<SignatureFeeConfig, SignError> {
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        Ok(mpc_contract.signature_fee.clone())
+    }
+
+    /// Updates the congestion-fee curve enforced by `sign`/`sign_many`/
+    /// `sign_delegated` and reported by `experimental_signature_deposit`.
+    /// Callable only as a self-call, same as other contract-config changes
+    /// (e.g. from a DAO-controlled config proposal).
+    #[handle_result]
+    pub fn set_signature_fee_config(
+        &mut self,
+        config: SignatureFeeConfig,
+    ) -> Result<(), SignError> {
+        if env::predecessor_account_id() != env::current_account_id() {
+            return Err(SignError::Unauthorized);
+        }
+        let Self::V1(mpc_contract) = self else {
+            return Err(SignError::InvalidContractState);
+        };
+        mpc_contract.signature_fee = config;
+        Ok(())
+    }
+
+    /// Charges `required` to `predecessor`: if a deposit is attached it is
+    /// checked and any excess refunded, otherwise `required` is debited from
+    /// the caller's prepaid balance (see [`Self::deposit`]).
+    fn charge_fee(
+        &mut self,
+        predecessor: &AccountId,
+        required: NearToken,
+    ) -> Result<(), SignError> {
+        let deposit = env::attached_deposit();
+        if deposit == NearToken::from_yoctonear(0) {
+            let Self::V1(mpc_contract) = self else {
+                return Err(SignError::InvalidContractState);
+            };
+            if !mpc_contract.debit_balance(predecessor, required) {
+                return Err(SignError::InsufficientDeposit {
+                    provided: mpc_contract.balance_of(predecessor),
+                    required,
+                });
+            }
+            return Ok(());
+        }
+
+        match deposit.checked_sub(required) {
+            None => Err(SignError::InsufficientDeposit {
+                provided: deposit,
+                required,
+            }),
+            Some(diff) => {
+                if diff > NearToken::from_yoctonear(0) {
+                    log!("refund excess deposit {diff} to {predecessor}");
+                    Promise::new(predecessor.clone()).transfer(diff);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Shared validation for a single signature request: looks up the public
+    /// key for the request's domain and checks the payload is well-formed for
+    /// that domain's curve. Fails identically to how the MPC nodes would
+    /// fail, so callers get an actionable error message either way.
+    fn validate_sign_request(&self, request: &SignRequest) -> Result<PublicKey, SignError> {
+        let public_key =
+            self.public_key(Some(request.domain_id))
+                .map_err(|_| SignError::DomainNotFound {
+                    domain_id: request.domain_id,
+                })?;
+
+        match public_key.curve_type() {
+            CurveType::SECP256K1 => {
+                let hash = request.payload.as_ecdsa().ok_or(SignError::CurveMismatch)?;
+                k256::Scalar::from_repr((*hash).into())
+                    .into_option()
+                    .ok_or_else(|| {
+                        SignError::MalformedPayload(
+                            "Ecdsa payload cannot be converted to Scalar".to_string(),
+                        )
+                    })?;
+            }
+            CurveType::ED25519 => {
+                request.payload.as_eddsa().ok_or(SignError::CurveMismatch)?;
+            }
+        }
+
+        Ok(public_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use near_sdk::test_utils::accounts;
+
+    fn test_contract() -> MpcContractV1 {
+        MpcContractV1 {
+            requests: near_sdk::store::IterableMap::new(b"r"),
+            batches: near_sdk::store::LookupMap::new(b"b"),
+            balances: near_sdk::store::LookupMap::new(b"l"),
+            signature_fee: SignatureFeeConfig::default(),
+            nonces: near_sdk::store::LookupMap::new(b"n"),
+            delegation_keys: near_sdk::store::LookupMap::new(b"k"),
+        }
+    }
+
+    #[test]
+    fn credit_then_debit_balance() {
+        let mut contract = test_contract();
+        let account = accounts(0);
+        contract.credit_balance(&account, NearToken::from_near(1));
+        assert_eq!(contract.balance_of(&account), NearToken::from_near(1));
+        assert!(contract.debit_balance(&account, NearToken::from_millinear(500)));
+        assert_eq!(
+            contract.balance_of(&account),
+            NearToken::from_millinear(500)
+        );
+    }
+
+    #[test]
+    fn debit_more_than_balance_fails_and_leaves_balance_untouched() {
+        let mut contract = test_contract();
+        let account = accounts(0);
+        contract.credit_balance(&account, NearToken::from_millinear(100));
+        assert!(!contract.debit_balance(&account, NearToken::from_near(1)));
+        assert_eq!(
+            contract.balance_of(&account),
+            NearToken::from_millinear(100)
+        );
+    }
+
+    /// Evaluates the congestion-fee curve that `required_signature_fee`
+    /// computes, written out here for given pending-request counts.
+    #[test]
+    fn signature_fee_curve_saturates_at_max_fee() {
+        let config = SignatureFeeConfig {
+            base_fee: NearToken::from_yoctonear(10),
+            slope: NearToken::from_yoctonear(5),
+            max_fee: NearToken::from_yoctonear(20),
+        };
+        let fee_for = |pending: u128| {
+            NearToken::from_yoctonear(
+                config
+                    .base_fee
+                    .as_yoctonear()
+                    .saturating_add(config.slope.as_yoctonear().saturating_mul(pending))
+                    .min(config.max_fee.as_yoctonear()),
+            )
+        };
+        assert_eq!(fee_for(0), NearToken::from_yoctonear(10));
+        assert_eq!(fee_for(1), NearToken::from_yoctonear(15));
+        // 100 pending requests would blow past max_fee without saturation.
+        assert_eq!(fee_for(100), config.max_fee);
+    }
+
+    #[test]
+    fn nonce_reuse_is_rejected() {
+        let mut contract = test_contract();
+        let signer = accounts(1);
+        assert_eq!(contract.next_nonce(&signer), 0);
+        assert!(contract.check_and_increment_nonce(&signer, 0));
+        assert_eq!(contract.next_nonce(&signer), 1);
+        // Nonce 0 has already been consumed; reusing it must fail.
+        assert!(!contract.check_and_increment_nonce(&signer, 0));
+        assert!(contract.check_and_increment_nonce(&signer, 1));
+    }
+
+    /// Builds a `DelegatedSignRequest` signed by `signing_key`, covering
+    /// `contract_account_id`/`nonce`/`deadline` the same way
+    /// `MpcContractV1::verify_delegated_request` expects.
+    fn sign_delegated_request(
+        signing_key: &SigningKey,
+        contract_account_id: &AccountId,
+        signer_id: AccountId,
+        nonce: u64,
+        deadline: u64,
+    ) -> DelegatedSignRequest {
+        let request = SignRequestArgs::default();
+        let message = serde_json::to_vec(&(
+            DELEGATED_SIGN_REQUEST_DOMAIN,
+            contract_account_id,
+            &request,
+            &nonce,
+            &deadline,
+        ))
+        .unwrap();
+        let signature = signing_key.sign(&message);
+        DelegatedSignRequest {
+            request,
+            signer_id,
+            nonce,
+            deadline,
+            signature: signature.to_bytes().to_vec(),
+        }
+    }
+
+    fn ed25519_public_key(signing_key: &SigningKey) -> PublicKey {
+        let mut bytes = vec![0u8]; // CurveType::ED25519 tag
+        bytes.extend_from_slice(signing_key.verifying_key().as_bytes());
+        PublicKey::try_from(bytes).unwrap()
+    }
+
+    #[test]
+    fn verify_delegated_request_accepts_a_correctly_signed_request() {
+        let mut contract = test_contract();
+        let contract_account_id = accounts(0);
+        let signer_id = accounts(1);
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        contract.register_delegation_key(signer_id.clone(), ed25519_public_key(&signing_key));
+
+        let signed_request = sign_delegated_request(
+            &signing_key,
+            &contract_account_id,
+            signer_id.clone(),
+            0,
+            u64::MAX,
+        );
+
+        assert!(contract
+            .verify_delegated_request(&contract_account_id, &signed_request)
+            .is_ok());
+        assert_eq!(contract.next_nonce(&signer_id), 1);
+    }
+
+    #[test]
+    fn verify_delegated_request_rejects_signature_from_an_unregistered_key() {
+        let mut contract = test_contract();
+        let contract_account_id = accounts(0);
+        let signer_id = accounts(1);
+        let victim_key = SigningKey::from_bytes(&[1u8; 32]);
+        let attacker_key = SigningKey::from_bytes(&[2u8; 32]);
+        // The victim's own key is what's registered, never a caller-supplied one.
+        contract.register_delegation_key(signer_id.clone(), ed25519_public_key(&victim_key));
+
+        // An attacker signs with their own key but claims to be `signer_id`.
+        let forged_request = sign_delegated_request(
+            &attacker_key,
+            &contract_account_id,
+            signer_id.clone(),
+            0,
+            u64::MAX,
+        );
+
+        assert!(matches!(
+            contract.verify_delegated_request(&contract_account_id, &forged_request),
+            Err(SignError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn verify_delegated_request_rejects_an_expired_deadline() {
+        let mut contract = test_contract();
+        let contract_account_id = accounts(0);
+        let signer_id = accounts(1);
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        contract.register_delegation_key(signer_id.clone(), ed25519_public_key(&signing_key));
+
+        let signed_request =
+            sign_delegated_request(&signing_key, &contract_account_id, signer_id, 0, 0);
+
+        let mut context = near_sdk::test_utils::VMContextBuilder::new();
+        context.block_timestamp(1);
+        near_sdk::testing_env!(context.build());
+
+        assert!(matches!(
+            contract.verify_delegated_request(&contract_account_id, &signed_request),
+            Err(SignError::Expired { deadline: 0 })
+        ));
+    }
+
+    #[test]
+    fn verify_delegated_request_rejects_a_reused_nonce() {
+        let mut contract = test_contract();
+        let contract_account_id = accounts(0);
+        let signer_id = accounts(1);
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        contract.register_delegation_key(signer_id.clone(), ed25519_public_key(&signing_key));
+
+        let first = sign_delegated_request(
+            &signing_key,
+            &contract_account_id,
+            signer_id.clone(),
+            0,
+            u64::MAX,
+        );
+        assert!(contract
+            .verify_delegated_request(&contract_account_id, &first)
+            .is_ok());
+
+        // Same nonce again, signed fresh but over an already-consumed nonce.
+        let replay =
+            sign_delegated_request(&signing_key, &contract_account_id, signer_id, 0, u64::MAX);
+        assert!(matches!(
+            contract.verify_delegated_request(&contract_account_id, &replay),
+            Err(SignError::NonceMismatch {
+                expected: 1,
+                provided: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn on_withdraw_transfer_recredits_balance_on_failed_transfer() {
+        let account_id = accounts(0);
+        let amount = NearToken::from_near(1);
+
+        let mut context = near_sdk::test_utils::VMContextBuilder::new();
+        context.predecessor_account_id(accounts(1));
+        near_sdk::testing_env!(
+            context.build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed]
+        );
+
+        let mut contract = VersionedMpcContract::V1(test_contract());
+        assert!(contract
+            .on_withdraw_transfer(account_id.clone(), amount)
+            .is_ok());
+        let VersionedMpcContract::V1(mpc_contract) = &contract else {
+            unreachable!()
+        };
+        assert_eq!(mpc_contract.balance_of(&account_id), amount);
     }
 }